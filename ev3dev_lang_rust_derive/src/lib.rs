@@ -0,0 +1,272 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta, Type};
+
+/// Derives a `Device` impl that delegates `get_attribute` to the struct's
+/// `driver: Driver` field, plus typed accessors (a getter, a setter, or
+/// both, depending on `access`) for each field annotated with
+/// `#[ev3_attribute(...)]`.
+///
+/// ```ignore
+/// #[derive(Device)]
+/// struct Motor {
+///     driver: Driver,
+///     #[ev3_attribute(name = "speed_sp", access = "rw", ty = "i32")]
+///     speed_sp: (),
+/// }
+/// ```
+///
+/// expands the annotated field into `fn get_speed_sp(&self) -> Ev3Result<i32>`
+/// and, because `access = "rw"`, `fn set_speed_sp(&self, v: i32) -> Ev3Result<()>`.
+/// Recognized keys: `name` (sysfs attribute name, required), `access`
+/// (`"r"`, `"w"` or `"rw"`, defaults to `"rw"`), `ty` (Rust type, required
+/// unless `list` is set), `rename` (the name after the `get_`/`set_` prefix
+/// of the Rust methods, defaults to `name`) and `list` (a unit flag
+/// selecting a `Vec<String>` getter via `get_vec`; no setter is generated
+/// for a `list` field).
+#[proc_macro_derive(Device, attributes(ev3_attribute))]
+pub fn derive_device(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let struct_name = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("#[derive(Device)] only supports structs with named fields"),
+        },
+        _ => panic!("#[derive(Device)] only supports structs"),
+    };
+
+    let driver_field = fields
+        .iter()
+        .find(|field| field.ident.as_ref().map_or(false, |ident| ident == "driver"))
+        .unwrap_or_else(|| panic!("#[derive(Device)] requires a `driver: Driver` field"))
+        .ident
+        .as_ref()
+        .unwrap();
+
+    let mut accessors = Vec::new();
+    for field in fields {
+        if let Some(spec) = parse_ev3_attribute_spec(&field.attrs) {
+            accessors.push(spec.into_accessors());
+        }
+    }
+
+    let expanded = quote! {
+        impl Device for #struct_name {
+            fn get_attribute(&self, name: &str) -> Attribute {
+                self.#driver_field.get_attribute(name)
+            }
+        }
+
+        impl #struct_name {
+            #(#accessors)*
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+struct AttributeSpec {
+    sysfs_name: String,
+    rust_name: syn::Ident,
+    access: String,
+    ty: Option<Type>,
+    list: bool,
+}
+
+impl AttributeSpec {
+    fn into_accessors(self) -> proc_macro2::TokenStream {
+        let sysfs_name = &self.sysfs_name;
+        let getter = syn::Ident::new(&format!("get_{}", self.rust_name), self.rust_name.span());
+        let setter = syn::Ident::new(&format!("set_{}", self.rust_name), self.rust_name.span());
+
+        let get_body = if self.list {
+            quote! { self.get_attribute(#sysfs_name).get_vec() }
+        } else {
+            quote! { self.get_attribute(#sysfs_name).get() }
+        };
+        let ret_ty = if self.list {
+            quote! { Vec<String> }
+        } else {
+            let ty = self.ty.as_ref().expect("#[ev3_attribute] needs `ty` unless `list` is set");
+            quote! { #ty }
+        };
+
+        let get_fn = if self.access.contains('r') {
+            quote! {
+                pub fn #getter(&self) -> Ev3Result<#ret_ty> {
+                    #get_body
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let set_fn = if self.access.contains('w') && !self.list {
+            let ty = self.ty.as_ref().expect("#[ev3_attribute] needs `ty` unless `list` is set");
+            quote! {
+                pub fn #setter(&self, value: #ty) -> Ev3Result<()> {
+                    self.get_attribute(#sysfs_name).set(value)
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        quote! {
+            #get_fn
+            #set_fn
+        }
+    }
+}
+
+fn parse_ev3_attribute_spec(attrs: &[syn::Attribute]) -> Option<AttributeSpec> {
+    let attr = attrs.iter().find(|attr| attr.path.is_ident("ev3_attribute"))?;
+    let meta = attr.parse_meta().expect("malformed #[ev3_attribute(...)] attribute");
+
+    let mut sysfs_name = None;
+    let mut access = "rw".to_owned();
+    let mut ty = None;
+    let mut rename = None;
+    let mut list = false;
+
+    if let Meta::List(meta_list) = meta {
+        for nested in meta_list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) => {
+                    if let Lit::Str(lit) = &nv.lit {
+                        if nv.path.is_ident("name") {
+                            sysfs_name = Some(lit.value());
+                        } else if nv.path.is_ident("access") {
+                            access = lit.value();
+                        } else if nv.path.is_ident("ty") {
+                            ty = Some(syn::parse_str::<Type>(&lit.value()).expect("invalid `ty`"));
+                        } else if nv.path.is_ident("rename") {
+                            rename = Some(lit.value());
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("list") => {
+                    list = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let sysfs_name = sysfs_name.expect("#[ev3_attribute] requires `name = \"...\"`");
+    let rust_name = rename.unwrap_or_else(|| sysfs_name.clone());
+    let rust_name = syn::Ident::new(&rust_name, proc_macro2::Span::call_site());
+
+    Some(AttributeSpec {
+        sysfs_name,
+        rust_name,
+        access,
+        ty,
+        list,
+    })
+}
+
+/// Derives `Display` plus a stable `code()` and an optional `help()` for an
+/// error enum, reading `#[ev3(code = "...", help = "...")]` off each variant.
+///
+/// Mirrors the way rustc's diagnostic macros turn per-variant attributes
+/// into the boilerplate a hand-written `Display` impl would otherwise need.
+#[proc_macro_derive(Ev3Diagnostic, attributes(ev3))]
+pub fn derive_ev3_diagnostic(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => panic!("#[derive(Ev3Diagnostic)] only supports enums"),
+    };
+
+    let mut display_arms = Vec::new();
+    let mut code_arms = Vec::new();
+    let mut help_arms = Vec::new();
+
+    for variant in variants {
+        let variant_ident = &variant.ident;
+        let (code, help) = parse_ev3_attr(&variant.attrs);
+        let code = code.unwrap_or_else(|| panic!(
+            "variant {} is missing #[ev3(code = \"...\")]",
+            variant_ident
+        ));
+
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote! { #name::#variant_ident { .. } },
+            Fields::Unnamed(_) => quote! { #name::#variant_ident(..) },
+            Fields::Unit => quote! { #name::#variant_ident },
+        };
+
+        display_arms.push(quote! {
+            #pattern => write!(f, "[{}] {:?}", #code, self),
+        });
+        code_arms.push(quote! {
+            #pattern => #code,
+        });
+        help_arms.push(match help {
+            Some(help) => quote! { #pattern => Some(#help), },
+            None => quote! { #pattern => None, },
+        });
+    }
+
+    let expanded = quote! {
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#display_arms)*
+                }
+            }
+        }
+
+        impl #name {
+            /// Stable, machine-matchable code such as `"EV3-0003"`.
+            pub fn code(&self) -> &'static str {
+                match self {
+                    #(#code_arms)*
+                }
+            }
+
+            /// A short remediation note for this variant, if one was given.
+            pub fn help(&self) -> Option<&'static str> {
+                match self {
+                    #(#help_arms)*
+                }
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+fn parse_ev3_attr(attrs: &[syn::Attribute]) -> (Option<String>, Option<String>) {
+    let mut code = None;
+    let mut help = None;
+
+    for attr in attrs {
+        if !attr.path.is_ident("ev3") {
+            continue;
+        }
+        let meta = attr.parse_meta().expect("malformed #[ev3(...)] attribute");
+        if let Meta::List(list) = meta {
+            for nested in list.nested {
+                if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                    if let Lit::Str(lit) = &nv.lit {
+                        if nv.path.is_ident("code") {
+                            code = Some(lit.value());
+                        } else if nv.path.is_ident("help") {
+                            help = Some(lit.value());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    (code, help)
+}
@@ -3,6 +3,8 @@ use std::rc::Rc;
 use std::fs::File;
 use std::collections::HashMap;
 
+use ev3dev_lang_rust_derive::{Device, Ev3Diagnostic};
+
 #[derive(Clone)]
 pub struct Driver {
     class_name: String,
@@ -25,18 +27,44 @@ impl Driver {
     }
 }
 
-#[derive(Debug)]
+/// Machine-readable error taxonomy for the crate.
+///
+/// Every variant carries an `#[ev3(code = "...", help = "...")]` attribute
+/// consumed by `#[derive(Ev3Diagnostic)]` to generate `Display`, `code()`
+/// and `help()`, so downstream tools can match on `code()` instead of
+/// scraping the message text.
+#[derive(Debug, Ev3Diagnostic)]
 pub enum Ev3Error {
-    InternalError {
+    #[ev3(code = "EV3-0001", help = "check that the device is plugged in and its kernel driver is loaded")]
+    NotConnected {
+        device: String,
+        port: Option<String>,
+    },
+
+    #[ev3(code = "EV3-0002", help = "an I/O error occurred while talking to the sysfs attribute")]
+    Io {
+        msg: String,
+    },
+
+    #[ev3(code = "EV3-0003", help = "the value read from the attribute could not be parsed into the requested type")]
+    ParseError {
+        attribute: String,
+        raw: String,
         msg: String,
     },
+
+    #[ev3(code = "EV3-0004", help = "the requested command is not part of this device's command set")]
+    UnsupportedCommand {
+        given: String,
+        available: Vec<String>,
+    },
 }
 
 pub type Ev3Result<T> = Result<T, Ev3Error>;
 
 impl From<std::io::Error> for Ev3Error {
     fn from(err: std::io::Error) -> Self {
-        Ev3Error::InternalError {
+        Ev3Error::Io {
             msg: format!("{}", err),
         }
     }
@@ -44,7 +72,7 @@ impl From<std::io::Error> for Ev3Error {
 
 impl From<std::string::FromUtf8Error> for Ev3Error {
     fn from(err: std::string::FromUtf8Error) -> Self {
-        Ev3Error::InternalError {
+        Ev3Error::Io {
             msg: format!("{}", err),
         }
     }
@@ -52,7 +80,9 @@ impl From<std::string::FromUtf8Error> for Ev3Error {
 
 impl From<std::num::ParseIntError> for Ev3Error {
     fn from(err: std::num::ParseIntError) -> Self {
-        Ev3Error::InternalError {
+        Ev3Error::ParseError {
+            attribute: String::new(),
+            raw: String::new(),
             msg: format!("{}", err),
         }
     }
@@ -60,6 +90,7 @@ impl From<std::num::ParseIntError> for Ev3Error {
 
 #[derive(Debug, Clone)]
 pub struct Attribute {
+    attribute_name: String,
     file: Rc<RefCell<File>>,
 }
 
@@ -71,6 +102,13 @@ pub trait Device {
     }
 
     fn set_command(&self, command: &str) -> Ev3Result<()> {
+        let available = self.get_commands()?;
+        if !available.iter().any(|c| c == command) {
+            return Err(Ev3Error::UnsupportedCommand {
+                given: command.to_owned(),
+                available,
+            });
+        }
         self.get_attribute("command").set_str_slice(command)
     }
 
@@ -81,17 +119,43 @@ pub trait Device {
     fn get_driver_name(&self) -> Ev3Result<String> {
         self.get_attribute("driver_name").get()
     }
+
+    /// Convenience wrapper around `Attribute::wait_until` for a named
+    /// attribute of this device.
+    fn wait_until<F: Fn(&str) -> bool>(
+        &self,
+        attribute_name: &str,
+        predicate: F,
+        timeout: Option<Duration>,
+    ) -> Ev3Result<bool> {
+        self.get_attribute(attribute_name).wait_until(predicate, timeout)
+    }
 }
 
 use std::os::unix::io::RawFd;
 use std::error::Error;
 use std::fs::{OpenOptions};
 use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+use libc::{nfds_t, poll, pollfd, POLLERR, POLLPRI};
 
 impl Attribute {
     pub fn new(class_name: &str, name: &str, attribute_name: &str) -> Ev3Result<Attribute> {
-        let file = OpenOptions::new().open(&"a")?;
+        let file = OpenOptions::new().open(&"a").map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Ev3Error::NotConnected {
+                    device: name.to_owned(),
+                    port: None,
+                }
+            } else {
+                Ev3Error::Io {
+                    msg: format!("{}", e),
+                }
+            }
+        })?;
         Ok(Attribute {
+            attribute_name: attribute_name.to_owned(),
             file: Rc::new(RefCell::new(file)),
         })
     }
@@ -109,13 +173,12 @@ impl Attribute {
         T: std::str::FromStr,
         <T as std::str::FromStr>::Err: Error,
     {
-        let value = self.get_str()?;
-        match value.parse::<T>() {
-            Ok(value) => Ok(value),
-            Err(e) => Err(Ev3Error::InternalError {
-                msg: format!("{}", e),
-            }),
-        }
+        let raw = self.get_str()?;
+        raw.parse::<T>().map_err(|e| Ev3Error::ParseError {
+            attribute: self.attribute_name.clone(),
+            raw,
+            msg: format!("{}", e),
+        })
     }
 
     pub fn set<T>(&self, value: T) -> Ev3Result<()>
@@ -137,4 +200,101 @@ impl Attribute {
     pub fn get_raw_fd(&self) -> RawFd {
         self.file.borrow().as_raw_fd()
     }
+
+    /// Blocks until `predicate` holds for the attribute's current value, or
+    /// `timeout` elapses (blocks forever if `timeout` is `None`).
+    ///
+    /// sysfs signals an attribute change as an exceptional condition on its
+    /// fd rather than readability, so this polls on `POLLPRI`/`POLLERR`
+    /// instead of busy-rereading the file.
+    pub fn wait_until<F: Fn(&str) -> bool>(
+        &self,
+        predicate: F,
+        timeout: Option<Duration>,
+    ) -> Ev3Result<bool> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+
+        loop {
+            if predicate(&self.get_str()?) {
+                return Ok(true);
+            }
+
+            let remaining = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) => Some(remaining),
+                    None => return Ok(false),
+                },
+                None => None,
+            };
+
+            if poll_raw_fds(&[self.get_raw_fd()], remaining)?.is_none() {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Blocks until at least one of `attrs` changes, returning the index of
+    /// the attribute that fired, so a control loop can wait on several
+    /// sensors at once without busy-polling.
+    pub fn wait_any(attrs: &[&Attribute], timeout: Option<Duration>) -> Ev3Result<usize> {
+        let fds: Vec<RawFd> = attrs.iter().map(|attr| attr.get_raw_fd()).collect();
+        poll_raw_fds(&fds, timeout)?.ok_or_else(|| Ev3Error::Io {
+            msg: "wait_any timed out before any attribute changed".to_owned(),
+        })
+    }
+}
+
+/// Polls `fds` for `POLLPRI`/`POLLERR` and returns the index of the first
+/// one ready, or `None` on timeout.
+fn poll_raw_fds(fds: &[RawFd], timeout: Option<Duration>) -> Ev3Result<Option<usize>> {
+    let mut poll_fds: Vec<pollfd> = fds
+        .iter()
+        .map(|&fd| pollfd {
+            fd,
+            events: POLLPRI | POLLERR,
+            revents: 0,
+        })
+        .collect();
+
+    let timeout_ms = match timeout {
+        Some(duration) => duration.as_millis().min(i32::MAX as u128) as i32,
+        None => -1,
+    };
+
+    let ready = unsafe { poll(poll_fds.as_mut_ptr(), poll_fds.len() as nfds_t, timeout_ms) };
+    if ready < 0 {
+        return Err(Ev3Error::Io {
+            msg: format!("{}", std::io::Error::last_os_error()),
+        });
+    }
+    if ready == 0 {
+        return Ok(None);
+    }
+
+    Ok(poll_fds
+        .iter()
+        .position(|pfd| pfd.revents & (POLLPRI | POLLERR) != 0))
+}
+
+/// A large/medium motor, driven entirely by `#[derive(Device)]`.
+///
+/// Each `#[ev3_attribute(...)]` field below expands into its `get_`
+/// accessor and, where writable, its `set_` accessor (the read-only `state`
+/// list gets only `get_state`) instead of being hand-written; the field itself
+/// carries no data and is never read at runtime.
+#[derive(Clone, Device)]
+pub struct Motor {
+    driver: Driver,
+
+    #[ev3_attribute(name = "speed_sp", access = "rw", ty = "i32")]
+    speed_sp: (),
+
+    #[ev3_attribute(name = "position", access = "rw", ty = "i32")]
+    position: (),
+
+    #[ev3_attribute(name = "duty_cycle_sp", access = "rw", ty = "i32", rename = "duty_cycle")]
+    duty_cycle_sp: (),
+
+    #[ev3_attribute(name = "state", access = "r", list)]
+    state: (),
 }
@@ -1,5 +1,8 @@
 use ev3dev_lang_rust_derive::{Device};
 
+mod ev3;
+mod repl;
+
 #[derive(Clone)]
 pub struct Driver { }
 
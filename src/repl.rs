@@ -0,0 +1,121 @@
+//! Interactive REPL for driving attached EV3 devices without recompiling.
+//!
+//! Modeled on the way `evcxr` keeps an evaluation context that accepts
+//! incremental commands and reports results: [`Repl`] owns a registry of
+//! discovered devices and a single `eval` entry point, so the same engine
+//! can power an interactive shell, a socket server, or a scripted test
+//! harness.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::ev3::{Attribute, Device, Driver, Ev3Error, Ev3Result};
+
+/// A device discovered by the REPL, addressable by its registry key.
+///
+/// Wraps a bare [`Driver`] in a `#[derive(Device)]` struct so dispatch goes
+/// through the same `Device::set_command`/`get_attribute` surface the rest
+/// of the crate uses, rather than a bespoke command path.
+#[derive(Clone, Device)]
+struct RegisteredDevice {
+    driver: Driver,
+}
+
+/// Owns the set of devices the REPL knows about and evaluates command lines
+/// against them.
+pub struct Repl {
+    devices: HashMap<String, RegisteredDevice>,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl {
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Registers a device under `key` (conventionally `<class>_<address>`,
+    /// e.g. `"motor_a"` or `"sensor_1"`).
+    pub fn register(&mut self, key: impl Into<String>, driver: Driver) {
+        self.devices.insert(key.into(), RegisteredDevice { driver });
+    }
+
+    /// Evaluates a single command line and returns the text to display.
+    ///
+    /// Transport-agnostic by design: a `stdin` loop, a socket handler, or a
+    /// test harness can all call this without knowing about each other.
+    /// Supported commands:
+    /// - `list`
+    /// - `<device> get <attribute>`
+    /// - `<device> set <attribute> <value...>`
+    pub fn eval(&mut self, line: &str) -> Ev3Result<String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.as_slice() {
+            ["list"] => {
+                let mut keys: Vec<&String> = self.devices.keys().collect();
+                keys.sort();
+                Ok(keys
+                    .into_iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            [key, "get", attribute] => {
+                let device = self.lookup(key)?;
+                Ok(describe(device.get_attribute(attribute).get::<String>()))
+            }
+            [key, "set", attribute, value_words @ ..] if !value_words.is_empty() => {
+                let device = self.lookup(key)?;
+                let value = value_words.join(" ");
+                let result = if *attribute == "command" {
+                    device.set_command(&value)
+                } else {
+                    device.get_attribute(attribute).set(value.clone())
+                };
+                Ok(describe(result.map(|()| format!("{} <- {}", attribute, value))))
+            }
+            _ => Err(Ev3Error::ParseError {
+                attribute: "command line".to_owned(),
+                raw: line.to_owned(),
+                msg: "expected `list`, `<device> get <attribute>` or `<device> set <attribute> <value...>`"
+                    .to_owned(),
+            }),
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Ev3Result<&RegisteredDevice> {
+        self.devices.get(key).ok_or_else(|| Ev3Error::NotConnected {
+            device: key.to_owned(),
+            port: None,
+        })
+    }
+}
+
+/// Renders a device operation's outcome the way the shell frontend prints
+/// it: the value on success, or the error's stable code and message.
+fn describe(result: Ev3Result<String>) -> String {
+    match result {
+        Ok(value) => value,
+        Err(e) => format!("{}: {}", e.code(), e),
+    }
+}
+
+/// Drives a [`Repl`] from stdin until EOF, printing each result (or error)
+/// to stdout. One of several possible frontends over the same `eval` loop.
+pub fn run_stdin(repl: &mut Repl) -> Ev3Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match repl.eval(&line) {
+            Ok(output) => writeln!(stdout, "{}", output)?,
+            Err(e) => writeln!(stdout, "{}: {}", e.code(), e)?,
+        }
+    }
+
+    Ok(())
+}